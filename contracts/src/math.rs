@@ -0,0 +1,130 @@
+//! Aritmética de ponto fixo verificada.
+//!
+//! Toda combinação de valores monetários e taxas passa por estas funções, que
+//! falham com MathOverflow em vez de estourar silenciosamente no WASM. Taxas
+//! usam a escala `PRECISION` (1_000_000 = 100%) ou pontos-base (`BPS`).
+//!
+//! Arredondamento: juros cobrados sobem (a favor do pool), principal e
+//! repasses descem. O resto dos repasses vai para o acumulador de poeira,
+//! varrido para a tesouraria, e o resto dos juros por segundo é levado ao
+//! próximo acúmulo, então cálculos repetidos não criam nem perdem valor.
+
+use crate::{Error, BPS, PRECISION};
+
+/// Calcula (a * b) / denominator sem estouro intermediário silencioso
+pub(crate) fn mul_div(a: i128, b: i128, denominator: i128) -> Result<i128, Error> {
+    a.checked_mul(b)
+        .and_then(|product| product.checked_div(denominator))
+        .ok_or(Error::MathOverflow)
+}
+
+/// Calcula (a * b) / denominator arredondando para cima
+pub(crate) fn mul_div_ceil(a: i128, b: i128, denominator: i128) -> Result<i128, Error> {
+    let product = a.checked_mul(b).ok_or(Error::MathOverflow)?;
+    let quotient = product.checked_div(denominator).ok_or(Error::MathOverflow)?;
+    if product % denominator > 0 {
+        return quotient.checked_add(1).ok_or(Error::MathOverflow);
+    }
+    Ok(quotient)
+}
+
+/// Aplica uma taxa na escala PRECISION, arredondando para baixo
+pub(crate) fn percent(amount: i128, rate: u32) -> Result<i128, Error> {
+    mul_div(amount, rate as i128, PRECISION as i128)
+}
+
+/// Aplica uma taxa na escala PRECISION, arredondando para cima
+pub(crate) fn percent_ceil(amount: i128, rate: u32) -> Result<i128, Error> {
+    mul_div_ceil(amount, rate as i128, PRECISION as i128)
+}
+
+/// Aplica uma taxa em pontos-base, arredondando para baixo
+pub(crate) fn bps(amount: i128, rate_bps: u32) -> Result<i128, Error> {
+    mul_div(amount, rate_bps as i128, BPS as i128)
+}
+
+/// Eleva uma base em ponto fixo (escala PRECISION) a um expoente inteiro
+pub(crate) fn pow_fixed(base: i128, exp: u32) -> Result<i128, Error> {
+    let precision = PRECISION as i128;
+    let mut result = precision;
+    let mut base = base;
+    let mut exp = exp;
+    while exp > 0 {
+        if exp & 1 == 1 {
+            result = mul_div(result, base, precision)?;
+        }
+        exp >>= 1;
+        if exp > 0 {
+            base = mul_div(base, base, precision)?;
+        }
+    }
+    Ok(result)
+}
+
+/// Parcela fixa da tabela Price: P * r * (1+r)^n / ((1+r)^n - 1)
+pub(crate) fn annuity_payment(amount: i128, monthly_rate: u32, months: u32) -> Result<i128, Error> {
+    if months == 0 {
+        return Ok(0);
+    }
+    if monthly_rate == 0 {
+        return Ok(amount / months as i128);
+    }
+
+    let precision = PRECISION as i128;
+    let factor = pow_fixed(precision + monthly_rate as i128, months)?;
+    let numerator = mul_div(percent(amount, monthly_rate)?, factor, precision)?;
+    mul_div(numerator, precision, factor - precision)
+}
+
+pub(crate) fn checked_mul_u32(a: u32, b: u32) -> Result<u32, Error> {
+    a.checked_mul(b).ok_or(Error::MathOverflow)
+}
+
+pub(crate) fn checked_add_u32(a: u32, b: u32) -> Result<u32, Error> {
+    a.checked_add(b).ok_or(Error::MathOverflow)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ONE: i128 = PRECISION as i128;
+
+    #[test]
+    fn test_mul_div_rounding() {
+        assert_eq!(mul_div(10, 1, 3), Ok(3));
+        assert_eq!(mul_div_ceil(10, 1, 3), Ok(4));
+        assert_eq!(mul_div_ceil(9, 1, 3), Ok(3));
+        assert_eq!(mul_div(i128::MAX, 2, 3), Err(Error::MathOverflow));
+        assert_eq!(mul_div(1, 1, 0), Err(Error::MathOverflow));
+    }
+
+    #[test]
+    fn test_percent_helpers() {
+        assert_eq!(percent(600 * ONE, 2 * PRECISION / 100), Ok(12 * ONE));
+        assert_eq!(percent(1, PRECISION / 2), Ok(0));
+        assert_eq!(percent_ceil(1, PRECISION / 2), Ok(1));
+        assert_eq!(bps(1_000, 250), Ok(25));
+        assert_eq!(bps(1_000, BPS), Ok(1_000));
+    }
+
+    #[test]
+    fn test_pow_fixed() {
+        assert_eq!(pow_fixed(2 * ONE, 0), Ok(ONE));
+        assert_eq!(pow_fixed(2 * ONE, 10), Ok(1024 * ONE));
+        // 1,01^12 ≈ 1,126825
+        let compounded = pow_fixed(ONE + ONE / 100, 12).unwrap();
+        assert!((compounded - 1_126_825).abs() <= 2);
+    }
+
+    #[test]
+    fn test_annuity_payment() {
+        assert_eq!(annuity_payment(1_000 * ONE, 0, 4), Ok(250 * ONE));
+        assert_eq!(annuity_payment(1_000 * ONE, PRECISION / 100, 0), Ok(0));
+        // Parcela de 12 meses a 1%: ≈ 88,85 por 1.000
+        let payment = annuity_payment(1_000 * ONE, PRECISION / 100, 12).unwrap();
+        assert!((payment - 88_848_789).abs() < 100);
+        assert_eq!(checked_add_u32(u32::MAX, 1), Err(Error::MathOverflow));
+        assert_eq!(checked_mul_u32(2, 3), Ok(6));
+    }
+}
@@ -0,0 +1,135 @@
+//! Contratos simulados para testes de integração, disponíveis com a feature
+//! `testutils`: um oráculo de preços ajustável e um token SEP-41 mínimo com
+//! emissão livre. Servem apenas para testes nativos; não entram no WASM
+//! publicado.
+
+use soroban_sdk::{contract, contractimpl, contracttype, panic_with_error, Address, Env, String};
+
+use crate::{Error, PRECISION};
+
+#[contracttype]
+enum MockKey {
+    Price,
+    PairPrice(Address, Address),
+    Decimals,
+    Name,
+    Symbol,
+    Balance(Address),
+    Allowance(Address, Address),
+}
+
+/// Oráculo com preço ajustável por par, ou um preço geral (padrão: 0,1 do
+/// token cotado), em escala PRECISION
+#[contract]
+pub struct MockOracle;
+
+#[contractimpl]
+impl MockOracle {
+    pub fn set_price(env: Env, price: i128) {
+        env.storage().instance().set(&MockKey::Price, &price);
+    }
+
+    pub fn set_pair_price(env: Env, base: Address, quote: Address, price: i128) {
+        env.storage().instance().set(&MockKey::PairPrice(base, quote), &price);
+    }
+
+    pub fn price(env: Env, base: Address, quote: Address) -> i128 {
+        env.storage().instance()
+            .get(&MockKey::PairPrice(base, quote))
+            .or_else(|| env.storage().instance().get(&MockKey::Price))
+            .unwrap_or(PRECISION as i128 / 10)
+    }
+}
+
+/// Token SEP-41 mínimo. Qualquer um pode emitir; transferências e
+/// aprovações exigem a autorização do titular. A aprovação não expira.
+#[contract]
+pub struct MockToken;
+
+#[contractimpl]
+impl MockToken {
+    pub fn initialize(env: Env, decimals: u32, name: String, symbol: String) {
+        env.storage().instance().set(&MockKey::Decimals, &decimals);
+        env.storage().instance().set(&MockKey::Name, &name);
+        env.storage().instance().set(&MockKey::Symbol, &symbol);
+    }
+
+    pub fn mint(env: Env, to: Address, amount: i128) {
+        Self::credit(&env, &to, amount);
+    }
+
+    pub fn allowance(env: Env, from: Address, spender: Address) -> i128 {
+        env.storage().persistent().get(&MockKey::Allowance(from, spender)).unwrap_or(0)
+    }
+
+    pub fn approve(env: Env, from: Address, spender: Address, amount: i128, _expiration_ledger: u32) {
+        from.require_auth();
+        env.storage().persistent().set(&MockKey::Allowance(from, spender), &amount);
+    }
+
+    pub fn balance(env: Env, id: Address) -> i128 {
+        env.storage().persistent().get(&MockKey::Balance(id)).unwrap_or(0)
+    }
+
+    pub fn transfer(env: Env, from: Address, to: Address, amount: i128) {
+        from.require_auth();
+        Self::debit(&env, &from, amount);
+        Self::credit(&env, &to, amount);
+    }
+
+    pub fn transfer_from(env: Env, spender: Address, from: Address, to: Address, amount: i128) {
+        spender.require_auth();
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::debit(&env, &from, amount);
+        Self::credit(&env, &to, amount);
+    }
+
+    pub fn burn(env: Env, from: Address, amount: i128) {
+        from.require_auth();
+        Self::debit(&env, &from, amount);
+    }
+
+    pub fn burn_from(env: Env, spender: Address, from: Address, amount: i128) {
+        spender.require_auth();
+        Self::spend_allowance(&env, &from, &spender, amount);
+        Self::debit(&env, &from, amount);
+    }
+
+    pub fn decimals(env: Env) -> u32 {
+        env.storage().instance().get(&MockKey::Decimals).unwrap_or(7)
+    }
+
+    pub fn name(env: Env) -> String {
+        env.storage().instance().get(&MockKey::Name).unwrap_or(String::from_str(&env, "Mock Token"))
+    }
+
+    pub fn symbol(env: Env) -> String {
+        env.storage().instance().get(&MockKey::Symbol).unwrap_or(String::from_str(&env, "MOCK"))
+    }
+}
+
+impl MockToken {
+    fn credit(env: &Env, to: &Address, amount: i128) {
+        if amount < 0 {
+            panic_with_error!(env, Error::InvalidAmount);
+        }
+        let balance = Self::balance(env.clone(), to.clone());
+        env.storage().persistent().set(&MockKey::Balance(to.clone()), &(balance + amount));
+    }
+
+    fn debit(env: &Env, from: &Address, amount: i128) {
+        let balance = Self::balance(env.clone(), from.clone());
+        if amount < 0 || balance < amount {
+            panic_with_error!(env, Error::InvalidAmount);
+        }
+        env.storage().persistent().set(&MockKey::Balance(from.clone()), &(balance - amount));
+    }
+
+    fn spend_allowance(env: &Env, from: &Address, spender: &Address, amount: i128) {
+        let allowance = Self::allowance(env.clone(), from.clone(), spender.clone());
+        if allowance < amount {
+            panic_with_error!(env, Error::InvalidAmount);
+        }
+        env.storage().persistent().set(&MockKey::Allowance(from.clone(), spender.clone()), &(allowance - amount));
+    }
+}
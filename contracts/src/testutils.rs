@@ -0,0 +1,145 @@
+//! Utilitários para testes de integração com o StellarCredit, disponíveis
+//! com a feature `testutils`. Cobrem o preparo comum: contrato inicializado,
+//! token e liquidez do pool, usuários com score definido, empréstimos já
+//! aprovados, avanço do ledger e o ciclo de vida completo de um empréstimo
+//! ao longo de vários ledgers.
+
+use soroban_sdk::testutils::{Address as _, Ledger};
+use soroban_sdk::{symbol_short, token, Address, Env};
+
+use crate::{
+    CreditScore, Error, Receipt, RepaymentType, StellarCreditContract, StellarCreditContractClient, DAY_IN_LEDGERS,
+    DEFAULT_AFTER_SECONDS, LEDGER_CLOSE_SECONDS, PRECISION,
+};
+
+/// Registra e inicializa o contrato com autorizações simuladas. Retorna o
+/// cliente e o administrador.
+pub fn register_contract(env: &Env) -> (StellarCreditContractClient<'_>, Address) {
+    env.mock_all_auths();
+    let contract_id = env.register_contract(None, StellarCreditContract);
+    let client = StellarCreditContractClient::new(env, &contract_id);
+    let admin = Address::generate(env);
+    client.initialize(&admin);
+    (client, admin)
+}
+
+/// Registra um token SEP-41 e o configura como token dos empréstimos
+pub fn register_token(env: &Env, client: &StellarCreditContractClient) -> Address {
+    let token = env.register_stellar_asset_contract(Address::generate(env));
+    client.set_token(&token);
+    token
+}
+
+/// Emite `amount` do token para `to`
+pub fn mint(env: &Env, token: &Address, to: &Address, amount: i128) {
+    token::StellarAssetClient::new(env, token).mint(to, &amount);
+}
+
+/// Deposita `amount` no pool por um credor novo. Retorna o credor.
+pub fn seed_pool(env: &Env, client: &StellarCreditContractClient, token: &Address, amount: i128) -> Address {
+    let lender = Address::generate(env);
+    mint(env, token, &lender, amount);
+    client.deposit(&lender, &amount);
+    lender
+}
+
+/// Cria um usuário com o score informado (0 a 1000) e métricas típicas de
+/// um bom pagador. O score é gravado diretamente; o próximo recálculo
+/// (por exemplo após um pagamento) volta a derivá-lo das métricas.
+pub fn create_user(env: &Env, client: &StellarCreditContractClient, score: u32) -> Address {
+    let address = Address::generate(env);
+    let credit_score = CreditScore {
+        address: address.clone(),
+        score: score.min(1000),
+        last_updated: env.ledger().timestamp(),
+        transaction_volume: 8000 * PRECISION as i128,
+        payment_punctuality: 95,
+        usage_frequency: 30,
+        diversification: 85,
+        avg_balance: 1500 * PRECISION as i128,
+        algorithm_version: client.get_score_algorithm(),
+    };
+    env.as_contract(&client.address, || StellarCreditContract::save_score(env, &credit_score));
+    address
+}
+
+/// Origina e aprova um empréstimo amortizado de `amount` em `months`
+/// parcelas para `borrower`. Retorna o id do empréstimo.
+pub fn originate_loan(client: &StellarCreditContractClient, borrower: &Address, amount: i128, months: u32) -> u64 {
+    let loan_id = client.request_loan(borrower, &amount, &months, &RepaymentType::Amortized);
+    client.approve_loan(&loan_id);
+    loan_id
+}
+
+/// Avança o timestamp em `seconds`, mantendo a sequência do ledger
+pub fn advance_time(env: &Env, seconds: u64) {
+    env.ledger().with_mut(|li| li.timestamp += seconds);
+}
+
+/// Avança `ledgers` ledgers, com o timestamp acompanhando o tempo médio de
+/// fechamento. Entradas com TTL menor que o avanço expiram no ambiente de
+/// teste.
+pub fn advance_ledgers(env: &Env, ledgers: u32) {
+    env.ledger().with_mut(|li| {
+        li.sequence_number += ledgers;
+        li.timestamp += ledgers as u64 * LEDGER_CLOSE_SECONDS;
+    });
+}
+
+/// Avança até `timestamp` (se estiver no futuro), com a sequência do ledger
+/// acompanhando o tempo decorrido
+pub fn advance_to(env: &Env, timestamp: u64) {
+    let now = env.ledger().timestamp();
+    if timestamp <= now {
+        return;
+    }
+    env.ledger().with_mut(|li| {
+        li.sequence_number += ((timestamp - now) / LEDGER_CLOSE_SECONDS) as u32;
+        li.timestamp = timestamp;
+    });
+}
+
+/// Eleva os TTLs mínimos e máximo do ambiente de teste para que contratos
+/// e entradas sobrevivam a meses de ledgers. Deve ser chamada antes de
+/// registrar os contratos.
+pub fn extend_ledger_ttls(env: &Env) {
+    env.ledger().with_mut(|li| {
+        li.min_temp_entry_ttl = 2 * 365 * DAY_IN_LEDGERS;
+        li.min_persistent_entry_ttl = 2 * 365 * DAY_IN_LEDGERS;
+        li.max_entry_ttl = 10 * 365 * DAY_IN_LEDGERS;
+    });
+}
+
+/// Avança até o vencimento da próxima parcela, emite o valor devido ao
+/// tomador e paga. Retorna o comprovante.
+pub fn pay_installment(env: &Env, client: &StellarCreditContractClient, token: &Address, loan_id: u64) -> Receipt {
+    advance_to(env, client.get_next_payment(&loan_id).due_at);
+    let due = client.get_next_payment(&loan_id).amount_due;
+    let borrower = client.get_loan(&loan_id).unwrap().borrower;
+    mint(env, token, &borrower, due);
+    client.repay_loan(&loan_id, &due, &None)
+}
+
+/// Paga as parcelas em dia, uma por vencimento, até a quitação. Retorna o
+/// número de parcelas pagas.
+pub fn run_to_completion(env: &Env, client: &StellarCreditContractClient, token: &Address, loan_id: u64) -> u32 {
+    let mut paid = 0;
+    while client.get_loan(&loan_id).unwrap().status == symbol_short!("APPROVED") {
+        pay_installment(env, client, token, loan_id);
+        paid += 1;
+    }
+    paid
+}
+
+/// Deixa a próxima parcela vencer sem pagamento e marca o default assim
+/// que o atraso (mais a tolerância da faixa) permitir, avançando um dia
+/// por vez. Retorna o timestamp do default.
+pub fn run_to_default(env: &Env, client: &StellarCreditContractClient, loan_id: u64) -> u64 {
+    advance_to(env, client.get_next_payment(&loan_id).due_at + DEFAULT_AFTER_SECONDS + 1);
+    while client.get_loan(&loan_id).unwrap().status == symbol_short!("APPROVED")
+        && client.try_mark_default(&loan_id) == Err(Ok(Error::InvalidStatus))
+    {
+        advance_ledgers(env, DAY_IN_LEDGERS);
+    }
+    env.ledger().timestamp()
+}